@@ -0,0 +1,1108 @@
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1256};
+use serde::Deserialize;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::MAX_HEMISTICHS;
+
+// Sanity check for the size of the input, in bytes (by default)
+pub const MAX_FILE_SIZE: u64 = 10_000;
+
+// A saved web page is mostly markup, so it may be this many times larger
+const HTML_SIZE_FACTOR: u64 = 100;
+
+// Redirects to follow when fetching input from a URL
+#[cfg(feature = "http")]
+const MAX_REDIRECTS: u32 = 5;
+
+// HTML elements whose contents are never shown as text
+const HIDDEN_ELEMENTS: [&str; 4] = ["head", "script", "style", "title"];
+
+// HTML elements that begin or end a line of text
+const BLOCK_ELEMENTS: [&str; 11] = [
+    "br", "div", "h1", "h2", "h3", "h4", "h5", "h6", "li", "p", "tr",
+];
+
+// Format of the input; if not given, it is inferred from the file extension
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Text,
+    Json,
+    Csv,
+    /// TEI XML, with hemistichs as <l> elements (requires the "tei" feature)
+    Xml,
+    /// HTML, e.g., a page saved from Ganjoor, with the markup stripped
+    Html,
+    /// Markdown, of which only fenced code blocks and blockquotes are read
+    Markdown,
+}
+
+impl Format {
+    fn from_path(path: &str) -> Self {
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::Json,
+            Some("csv") => Self::Csv,
+            Some("xml") => Self::Xml,
+            Some("html" | "htm") => Self::Html,
+            Some("md" | "markdown") => Self::Markdown,
+            _ => Self::Text,
+        }
+    }
+}
+
+// Options affecting how input is read
+#[derive(Clone, Copy, Debug)]
+pub struct InputOptions {
+    pub format: Option<Format>,
+    pub csv_header: bool,
+    pub encoding: Option<&'static Encoding>,
+    pub max_bytes: u64,
+    pub read_all: bool,
+    #[cfg_attr(not(feature = "http"), allow(dead_code))]
+    pub timeout: Duration,
+}
+
+// The hemistichs of a poem, along with the encoding it was decoded from, if
+// that was anything other than UTF-8, and whether only the beginning of a
+// long input was read
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Poem {
+    pub hemistichs: Vec<Hemistich>,
+    pub encoding: Option<&'static str>,
+    pub truncated: bool,
+}
+
+// One line of input, with its line number in the source, an optional label
+// (e.g., a row number from a CSV file) to be shown in place of its sequential
+// number, and whether it is the first or second misra of its bayt, if the
+// source says so
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hemistich {
+    pub text: String,
+    pub line_no: usize,
+    pub label: Option<String>,
+    pub misra: Option<Misra>,
+}
+
+impl Hemistich {
+    fn unlabeled(text: &str, line_no: usize) -> Self {
+        Self {
+            text: text.to_string(),
+            line_no,
+            label: None,
+            misra: None,
+        }
+    }
+}
+
+// Position of a hemistich within its bayt
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Misra {
+    First,
+    Second,
+}
+
+// Number lines from one, as they would be shown in an editor
+fn numbered_lines(text: &str) -> Vec<Hemistich> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| Hemistich::unlabeled(line, i + 1))
+        .collect()
+}
+
+// A poem stored as JSON, either with a plain array of hemistichs or as
+// exported by the Ganjoor API; any other fields are ignored
+#[derive(Debug, Deserialize)]
+struct JsonPoem {
+    hemistichs: Option<Vec<String>>,
+    verses: Option<Vec<GanjoorVerse>>,
+}
+
+// A verse (i.e., hemistich) from the Ganjoor API, with its place in the poem
+// and its position: 0 or 2 for a first misra, 1 or 3 for a second, and
+// anything else for a single line or paragraph
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GanjoorVerse {
+    text: String,
+    v_order: i64,
+    #[serde(alias = "versePosition")]
+    position: Option<i64>,
+}
+
+// A file found among the inputs, either to be analyzed (possibly from within
+// a ZIP archive, by its name there) or skipped (with the reason why)
+#[derive(Debug)]
+pub enum InputFile {
+    Analyze(String),
+    #[cfg_attr(not(feature = "zip"), allow(dead_code))]
+    Archived(String, String),
+    Skip(String, &'static str),
+}
+
+pub fn load_poem(path: &str, options: InputOptions) -> Result<Poem> {
+    // Read from stdin if the path is "-"
+    let ((bytes, truncated), source, format) = if path == "-" {
+        let format = options.format.unwrap_or(Format::Text);
+        let bytes = read_poem(io::stdin().lock(), format, options, "stdin")?;
+        (bytes, "stdin", format)
+    } else if is_url(path) {
+        let (bytes, format) = fetch_poem(path, options)?;
+        (bytes, path, format)
+    } else {
+        let file = File::open(path).map_err(|e| anyhow!("Could not open {path}: {e}"))?;
+        let format = options.format.unwrap_or_else(|| Format::from_path(path));
+        let bytes = read_poem(file, format, options, path)?;
+        (bytes, path, format)
+    };
+
+    finish_poem(&bytes, truncated, format, options, source)
+}
+
+fn finish_poem(
+    bytes: &[u8],
+    truncated: bool,
+    format: Format,
+    options: InputOptions,
+    source: &str,
+) -> Result<Poem> {
+    let (contents, encoding) = decode_bytes(bytes, options.encoding, source)?;
+
+    Ok(Poem {
+        hemistichs: decode_poem(&contents, format, options, source)?,
+        encoding: (encoding != UTF_8).then(|| encoding.name()),
+        truncated,
+    })
+}
+
+pub fn is_url(path: &str) -> bool {
+    let lowercase = path.to_ascii_lowercase();
+    lowercase.starts_with("http://") || lowercase.starts_with("https://")
+}
+
+#[cfg(feature = "http")]
+fn fetch_poem(url: &str, options: InputOptions) -> Result<((Vec<u8>, bool), Format)> {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(options.timeout))
+        .max_redirects(MAX_REDIRECTS)
+        .http_status_as_error(false)
+        .build()
+        .into();
+
+    let mut response = agent
+        .get(url)
+        .call()
+        .map_err(|e| anyhow!("Could not fetch {url}: {e}"))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(anyhow!(
+            "Could not fetch {url}: the server responded {status}"
+        ));
+    }
+
+    // A web page may have no extension to go by
+    let format = options.format.unwrap_or_else(|| {
+        let format = Format::from_path(url.split(['?', '#']).next().unwrap_or(url));
+        if format == Format::Text && response.body().mime_type() == Some("text/html") {
+            Format::Html
+        } else {
+            format
+        }
+    });
+
+    // Don't download what would be rejected anyway; plain text can still be
+    // read in part
+    let too_large = response
+        .body()
+        .content_length()
+        .is_some_and(|len| len > size_limit(format, options));
+    if too_large && format != Format::Text {
+        return Err(anyhow!("The input from {url} appears suspiciously large"));
+    }
+
+    // The size of the body is checked as it is read
+    let reader = response.body_mut().with_config().limit(u64::MAX).reader();
+    Ok((read_poem(reader, format, options, url)?, format))
+}
+
+#[cfg(not(feature = "http"))]
+fn fetch_poem(url: &str, _options: InputOptions) -> Result<((Vec<u8>, bool), Format)> {
+    Err(anyhow!(
+        "Could not fetch {url}: this build lacks the \"http\" feature"
+    ))
+}
+
+pub fn collect_dir(dir: &Path, input_files: &mut Vec<InputFile>) -> Result<()> {
+    // Sort entries by path so that output is reproducible
+    let mut entries = fs::read_dir(dir)
+        .map_err(|e| anyhow!("Could not read directory {}: {e}", dir.display()))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    for entry in entries {
+        let display = entry.display().to_string();
+        let hidden = entry
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+
+        // A link to a directory could lead back to where it is, or above it,
+        // so only real directories are searched
+        let symlink = fs::symlink_metadata(&entry)?.file_type().is_symlink();
+
+        if entry.is_dir() {
+            if hidden {
+                input_files.push(InputFile::Skip(display, "hidden directory"));
+            } else if symlink {
+                input_files.push(InputFile::Skip(display, "link to a directory"));
+            } else {
+                collect_dir(&entry, input_files)?;
+            }
+        } else if entry.extension().is_some_and(|ext| ext == "txt") {
+            if hidden {
+                input_files.push(InputFile::Skip(display, "hidden file"));
+            } else {
+                input_files.push(InputFile::Analyze(display));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn is_zip(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+#[cfg(feature = "zip")]
+pub fn collect_zip(path: &str, input_files: &mut Vec<InputFile>) -> Result<()> {
+    let file = File::open(path).map_err(|e| anyhow!("Could not open {path}: {e}"))?;
+    let mut archive =
+        zip::ZipArchive::new(file).map_err(|e| anyhow!("Could not read archive {path}: {e}"))?;
+
+    // Sort entries by name, as for directories; names include any directories
+    // within the archive
+    let mut entries = Vec::new();
+    for i in 0..archive.len() {
+        let entry = archive
+            .by_index(i)
+            .map_err(|e| anyhow!("Could not read archive {path}: {e}"))?;
+        let txt = Path::new(entry.name())
+            .extension()
+            .is_some_and(|ext| ext == "txt");
+        if entry.is_file() && txt {
+            entries.push(entry.name().to_string());
+        }
+    }
+    entries.sort();
+
+    for name in entries {
+        let hidden = name.split('/').any(|part| part.starts_with('.'));
+
+        if hidden {
+            input_files.push(InputFile::Skip(format!("{path}/{name}"), "hidden file"));
+        } else {
+            input_files.push(InputFile::Archived(path.to_string(), name));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "zip"))]
+pub fn collect_zip(path: &str, _input_files: &mut Vec<InputFile>) -> Result<()> {
+    Err(anyhow!(
+        "Could not read archive {path}: this build lacks the \"zip\" feature"
+    ))
+}
+
+#[cfg(feature = "zip")]
+pub fn load_archived(path: &str, name: &str, options: InputOptions) -> Result<Poem> {
+    let source = format!("{path}/{name}");
+
+    let file = File::open(path).map_err(|e| anyhow!("Could not open {path}: {e}"))?;
+    let mut archive =
+        zip::ZipArchive::new(file).map_err(|e| anyhow!("Could not read archive {path}: {e}"))?;
+    let entry = archive
+        .by_name(name)
+        .map_err(|e| anyhow!("Could not read {source}: {e}"))?;
+
+    let format = options.format.unwrap_or(Format::Text);
+    let (bytes, truncated) = read_poem(entry, format, options, &source)?;
+
+    finish_poem(&bytes, truncated, format, options, &source)
+}
+
+#[cfg(not(feature = "zip"))]
+pub fn load_archived(path: &str, _name: &str, _options: InputOptions) -> Result<Poem> {
+    Err(anyhow!(
+        "Could not read archive {path}: this build lacks the \"zip\" feature"
+    ))
+}
+
+pub fn load_text(text: &str, options: InputOptions) -> Result<Poem> {
+    load_str(text, options, "--text")
+}
+
+#[cfg(feature = "clipboard")]
+pub fn load_clipboard(options: InputOptions) -> Result<Poem> {
+    // A headless system (e.g., over SSH) may have no clipboard at all
+    let mut clipboard = arboard::Clipboard::new().map_err(|e| {
+        anyhow!("Could not access the clipboard ({e}); try piping the poem to --input - instead")
+    })?;
+
+    let text = clipboard.get_text().map_err(|e| match e {
+        arboard::Error::ContentNotAvailable => {
+            anyhow!("The clipboard is empty, or holds something other than text")
+        }
+        e => anyhow!("Could not read from the clipboard: {e}"),
+    })?;
+
+    if text.trim().is_empty() {
+        return Err(anyhow!("The clipboard is empty, or holds only whitespace"));
+    }
+
+    load_str(&text, options, "the clipboard")
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub fn load_clipboard(_options: InputOptions) -> Result<Poem> {
+    Err(anyhow!(
+        "Could not read from the clipboard: this build lacks the \"clipboard\" feature; try piping the poem to --input - instead"
+    ))
+}
+
+fn load_str(text: &str, options: InputOptions, source: &str) -> Result<Poem> {
+    // Apply the same sanity check as for files
+    if text.len() as u64 > options.max_bytes {
+        return Err(anyhow!("The text from {source} appears suspiciously large"));
+    }
+
+    let format = options.format.unwrap_or(Format::Text);
+
+    Ok(Poem {
+        hemistichs: decode_poem(text, format, options, source)?,
+        encoding: None,
+        truncated: false,
+    })
+}
+
+pub fn parse_encoding(label: &str) -> Result<&'static Encoding> {
+    Encoding::for_label(label.as_bytes()).ok_or_else(|| anyhow!("Unknown encoding: {label}"))
+}
+
+fn read_poem<R: Read>(
+    reader: R,
+    format: Format,
+    options: InputOptions,
+    source: &str,
+) -> Result<(Vec<u8>, bool)> {
+    // Apply a sanity check for the size of the input, counting bytes as they
+    // are read; one byte more than the limit is enough to know
+    let max_bytes = size_limit(format, options);
+    let read_error = |e: io::Error| anyhow!("Could not read from {source}: {e}");
+
+    // Read line by line, noting where the first forty non-empty lines end, in
+    // case the input turns out to be too long to read in full
+    let mut reader = BufReader::new(reader);
+    let mut bytes = Vec::new();
+    let mut non_empty_lines = 0;
+    let mut head_end = None;
+
+    while bytes.len() as u64 <= max_bytes {
+        let start = bytes.len();
+        let remaining = max_bytes + 1 - start as u64;
+        let read = (&mut reader)
+            .take(remaining)
+            .read_until(b'\n', &mut bytes)
+            .map_err(read_error)?;
+        if read == 0 {
+            break;
+        }
+
+        let line = &bytes[start..];
+        if head_end.is_none()
+            && line.ends_with(b"\n")
+            && line.iter().any(|b| !b.is_ascii_whitespace())
+        {
+            non_empty_lines += 1;
+            if non_empty_lines == MAX_HEMISTICHS {
+                head_end = Some(bytes.len());
+            }
+        }
+    }
+
+    if bytes.len() as u64 <= max_bytes {
+        return Ok((bytes, false));
+    }
+
+    // A long plain-text poem (e.g., a masnavi) is fine, though. Read the rest
+    // of it if the hemistichs to analyze may lie anywhere in it
+    if format == Format::Text && options.read_all {
+        reader.read_to_end(&mut bytes).map_err(read_error)?;
+        return Ok((bytes, false));
+    }
+
+    // Otherwise stop after the first forty lines. Lines can't be counted this
+    // way in UTF-16, where a newline is two bytes
+    let utf16 = Encoding::for_bom(&bytes).map_or(options.encoding, |(encoding, _)| Some(encoding));
+    let utf16 = matches!(utf16, Some(e) if e == UTF_16LE || e == UTF_16BE);
+    if format == Format::Text && !utf16 {
+        if let Some(end) = head_end {
+            bytes.truncate(end);
+            return Ok((bytes, true));
+        }
+    }
+
+    Err(anyhow!(
+        "The input from {source} appears suspiciously large"
+    ))
+}
+
+fn size_limit(format: Format, options: InputOptions) -> u64 {
+    if format == Format::Html {
+        options.max_bytes.saturating_mul(HTML_SIZE_FACTOR)
+    } else {
+        options.max_bytes
+    }
+}
+
+fn decode_bytes(
+    bytes: &[u8],
+    encoding: Option<&'static Encoding>,
+    source: &str,
+) -> Result<(String, &'static Encoding)> {
+    // Unless an encoding was specified, look for a UTF-16 (or UTF-8)
+    // byte-order mark, then try UTF-8; older Persian text files are often
+    // Windows-1256
+    let encoding = encoding.unwrap_or_else(|| {
+        if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+            encoding
+        } else if std::str::from_utf8(bytes).is_ok() {
+            UTF_8
+        } else {
+            WINDOWS_1256
+        }
+    });
+
+    let (contents, had_errors) = encoding.decode_with_bom_removal(bytes);
+    if had_errors {
+        return Err(anyhow!(
+            "The input from {source} could not be decoded as {}",
+            encoding.name()
+        ));
+    }
+
+    Ok((contents.into_owned(), encoding))
+}
+
+// Split the contents of the input into hemistichs
+fn decode_poem(
+    contents: &str,
+    format: Format,
+    options: InputOptions,
+    source: &str,
+) -> Result<Vec<Hemistich>> {
+    // Files saved by Windows editors may begin with a byte-order mark and use
+    // \r\n (or even lone \r) line endings
+    let contents = contents
+        .strip_prefix('\u{feff}')
+        .unwrap_or(contents)
+        .replace("\r\n", "\n")
+        .replace('\r', "\n");
+    let contents = contents.as_str();
+
+    match format {
+        Format::Text => Ok(numbered_lines(contents)),
+        Format::Json => load_poem_json(contents, source),
+        Format::Csv => load_poem_csv(contents, options.csv_header, source),
+        Format::Xml => load_poem_tei(contents, source),
+        Format::Html => Ok(load_poem_html(contents)),
+        Format::Markdown => load_poem_markdown(contents, source),
+    }
+}
+
+fn load_poem_json(contents: &str, source: &str) -> Result<Vec<Hemistich>> {
+    let poem: JsonPoem = serde_json::from_str(contents)
+        .map_err(|e| anyhow!("Could not parse JSON from {source}: {e}"))?;
+
+    if let Some(verses) = poem.verses {
+        return load_poem_ganjoor(verses, source);
+    }
+
+    match poem.hemistichs {
+        None => Err(anyhow!(
+            "The JSON from {source} has no \"hemistichs\" array"
+        )),
+        Some(hemistichs) if hemistichs.is_empty() => Err(anyhow!(
+            "The \"hemistichs\" array in the JSON from {source} is empty"
+        )),
+        Some(hemistichs) => Ok(numbered_lines(&hemistichs.join("\n"))),
+    }
+}
+
+fn load_poem_ganjoor(mut verses: Vec<GanjoorVerse>, source: &str) -> Result<Vec<Hemistich>> {
+    if verses.is_empty() {
+        return Err(anyhow!(
+            "The \"verses\" array in the JSON from {source} is empty"
+        ));
+    }
+
+    // Verses are not guaranteed to arrive in order
+    verses.sort_by_key(|verse| verse.v_order);
+
+    Ok(verses
+        .iter()
+        .enumerate()
+        .map(|(i, verse)| Hemistich {
+            misra: match verse.position {
+                Some(0 | 2) => Some(Misra::First),
+                Some(1 | 3) => Some(Misra::Second),
+                _ => None,
+            },
+            ..Hemistich::unlabeled(&verse.text, i + 1)
+        })
+        .collect())
+}
+
+fn load_poem_csv(contents: &str, has_header: bool, source: &str) -> Result<Vec<Hemistich>> {
+    // Rows may have one column (the hemistich) or two (hemistich and label)
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(has_header)
+        .flexible(true)
+        .from_reader(contents.as_bytes());
+
+    let mut hemistichs = Vec::new();
+
+    for record in reader.records() {
+        let record = record.map_err(|e| anyhow!("Could not parse CSV from {source}: {e}"))?;
+
+        let text = record.get(0).unwrap_or_default().to_string();
+        let line_no = record.position().map_or(0, csv::Position::line);
+        let label = record
+            .get(1)
+            .map(str::trim)
+            .filter(|label| !label.is_empty())
+            .map(String::from);
+
+        #[allow(clippy::cast_possible_truncation)]
+        hemistichs.push(Hemistich {
+            text,
+            line_no: line_no as usize,
+            label,
+            misra: None,
+        });
+    }
+
+    Ok(hemistichs)
+}
+
+fn load_poem_html(contents: &str) -> Vec<Hemistich> {
+    // Lines of text, each with the line of the source on which it begins
+    let mut lines = vec![(String::new(), 1)];
+    let mut line_no = 1;
+    let mut rest = contents;
+
+    while let Some(c) = rest.chars().next() {
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            let end = comment.find("-->").map_or(comment.len(), |i| i + 3);
+            line_no += comment[..end].matches('\n').count();
+            rest = &comment[end..];
+        } else if c == '<' {
+            let end = rest.find('>').map_or(rest.len(), |i| i + 1);
+            let tag = &rest[1..end];
+            line_no += tag.matches('\n').count();
+            rest = &rest[end..];
+
+            let closing = tag.starts_with('/');
+            let name = tag
+                .trim_start_matches('/')
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .next()
+                .unwrap_or_default()
+                .to_ascii_lowercase();
+
+            if !closing && HIDDEN_ELEMENTS.contains(&name.as_str()) {
+                // Skip ahead to the closing tag (lowercasing ASCII doesn't
+                // change byte offsets)
+                let end = rest
+                    .to_ascii_lowercase()
+                    .find(&format!("</{name}"))
+                    .unwrap_or(rest.len());
+                line_no += rest[..end].matches('\n').count();
+                rest = &rest[end..];
+            } else if BLOCK_ELEMENTS.contains(&name.as_str()) {
+                lines.push((String::new(), line_no));
+            }
+        } else {
+            let (decoded, len) = if c == '&' {
+                decode_entity(rest).unwrap_or(('&', 1))
+            } else {
+                (c, c.len_utf8())
+            };
+            rest = &rest[len..];
+
+            let (line, start) = lines.last_mut().unwrap();
+            if !decoded.is_whitespace() && line.trim().is_empty() {
+                *start = line_no;
+            }
+            line.push(decoded);
+
+            if c == '\n' {
+                line_no += 1;
+            }
+        }
+    }
+
+    // Line breaks and other whitespace in the source have no meaning
+    lines
+        .iter()
+        .map(|(line, line_no)| {
+            (
+                line.split_whitespace().collect::<Vec<_>>().join(" "),
+                line_no,
+            )
+        })
+        .filter(|(line, _)| !line.is_empty())
+        .map(|(line, line_no)| Hemistich::unlabeled(&line, *line_no))
+        .collect()
+}
+
+// Decode the character reference at the start of some HTML text, returning the
+// character and the length of the reference
+fn decode_entity(text: &str) -> Option<(char, usize)> {
+    let end = text.find(';').filter(|&end| end <= 32)?;
+    let name = &text[1..end];
+
+    // Besides markup and the invisible characters of Persian text, the
+    // punctuation, brackets, and spaces an edition is likely to use
+    let c = match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" | "NonBreakingSpace" => '\u{a0}',
+        "ensp" => '\u{2002}',
+        "emsp" => '\u{2003}',
+        "thinsp" | "ThinSpace" => '\u{2009}',
+        "zwnj" => '\u{200c}',
+        "zwj" => '\u{200d}',
+        "lrm" => '\u{200e}',
+        "rlm" => '\u{200f}',
+        "ZeroWidthSpace" => '\u{200b}',
+        "NoBreak" => '\u{2060}',
+        "shy" => '\u{ad}',
+        "laquo" => '«',
+        "raquo" => '»',
+        "lsquo" | "OpenCurlyQuote" => '‘',
+        "rsquo" | "rsquor" | "CloseCurlyQuote" => '’',
+        "ldquo" | "OpenCurlyDoubleQuote" => '“',
+        "rdquo" | "rdquor" | "CloseCurlyDoubleQuote" => '”',
+        "hellip" | "mldr" => '…',
+        "mdash" => '—',
+        "ndash" => '–',
+        "period" => '.',
+        "comma" => ',',
+        "colon" => ':',
+        "semi" => ';',
+        "quest" => '?',
+        "excl" => '!',
+        "lpar" => '(',
+        "rpar" => ')',
+        "lsqb" | "lbrack" => '[',
+        "rsqb" | "rbrack" => ']',
+        "lcub" | "lbrace" => '{',
+        "rcub" | "rbrace" => '}',
+        _ => {
+            let number = name.strip_prefix('#')?;
+            let code = if let Some(hex) = number.strip_prefix(['x', 'X']) {
+                u32::from_str_radix(hex, 16).ok()?
+            } else {
+                number.parse().ok()?
+            };
+            char::from_u32(code)?
+        }
+    };
+
+    Some((c, end + 1))
+}
+
+fn load_poem_markdown(contents: &str, source: &str) -> Result<Vec<Hemistich>> {
+    // Verse is kept in fenced code blocks or blockquotes; anything else is
+    // commentary. Blocks are kept apart by blank lines
+    let mut blocks: Vec<Vec<Hemistich>> = Vec::new();
+    let mut fence: Option<&str> = None;
+    let mut in_quote = false;
+
+    for (i, line) in contents.lines().enumerate() {
+        let trimmed = line.trim_start();
+
+        if let Some(open) = fence {
+            if trimmed.starts_with(open) && trimmed.trim_start_matches(&open[..1]).trim().is_empty()
+            {
+                fence = None;
+            } else {
+                blocks
+                    .last_mut()
+                    .unwrap()
+                    .push(Hemistich::unlabeled(line, i + 1));
+            }
+        } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            // The closing fence must be made of the same character, and be at
+            // least as long
+            let c = &trimmed[..1];
+            let len = trimmed.len() - trimmed.trim_start_matches(c).len();
+            fence = Some(&trimmed[..len]);
+            in_quote = false;
+            blocks.push(Vec::new());
+        } else if let Some(quoted) = trimmed.strip_prefix('>') {
+            if !in_quote {
+                blocks.push(Vec::new());
+                in_quote = true;
+            }
+            let quoted = quoted.trim_start_matches(['>', ' ']);
+            blocks
+                .last_mut()
+                .unwrap()
+                .push(Hemistich::unlabeled(quoted, i + 1));
+        } else {
+            in_quote = false;
+        }
+    }
+
+    if blocks
+        .iter()
+        .all(|block| block.iter().all(|hem| hem.text.trim().is_empty()))
+    {
+        return Err(anyhow!(
+            "No verse was found in fenced code blocks or blockquotes in the Markdown from {source}"
+        ));
+    }
+
+    let mut hemistichs = Vec::new();
+    for block in blocks {
+        if !hemistichs.is_empty() {
+            let line_no = block.first().map_or(0, |hem| hem.line_no);
+            hemistichs.extend([
+                Hemistich::unlabeled("", line_no),
+                Hemistich::unlabeled("", line_no),
+            ]);
+        }
+        hemistichs.extend(block);
+    }
+
+    Ok(hemistichs)
+}
+
+#[cfg(feature = "tei")]
+fn load_poem_tei(contents: &str, source: &str) -> Result<Vec<Hemistich>> {
+    let doc = roxmltree::Document::parse(contents)
+        .map_err(|e| anyhow!("Could not parse XML from {source}: {e}"))?;
+
+    // Take the <l> elements in document order, wherever they appear
+    let hemistichs: Vec<Hemistich> = doc
+        .descendants()
+        .filter(|node| node.has_tag_name("l"))
+        .map(|l| {
+            let mut text = String::new();
+            collect_verse_text(l, &mut text);
+            let line_no = doc.text_pos_at(l.range().start).row as usize;
+            Hemistich::unlabeled(
+                &text.split_whitespace().collect::<Vec<_>>().join(" "),
+                line_no,
+            )
+        })
+        .collect();
+
+    if hemistichs.is_empty() {
+        return Err(anyhow!(
+            "No <l> elements were found in the XML from {source}"
+        ));
+    }
+
+    Ok(hemistichs)
+}
+
+#[cfg(feature = "tei")]
+fn collect_verse_text(node: roxmltree::Node, text: &mut String) {
+    for child in node.children() {
+        if child.is_text() {
+            text.push_str(child.text().unwrap_or_default());
+        // Notes and critical apparatus are not part of the verse
+        } else if child.is_element() && !matches!(child.tag_name().name(), "note" | "app") {
+            collect_verse_text(child, text);
+        }
+    }
+}
+
+#[cfg(not(feature = "tei"))]
+fn load_poem_tei(_contents: &str, source: &str) -> Result<Vec<Hemistich>> {
+    Err(anyhow!(
+        "Could not read XML from {source}: this build lacks the \"tei\" feature"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEN_HEMISTICHS: &str = include_str!("../tests/fixtures/ten-hemistichs.txt");
+
+    fn options() -> InputOptions {
+        InputOptions {
+            format: None,
+            csv_header: false,
+            encoding: None,
+            max_bytes: MAX_FILE_SIZE,
+            read_all: false,
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    #[test]
+    fn reads_poem_from_reader() {
+        let (bytes, truncated) =
+            read_poem(TEN_HEMISTICHS.as_bytes(), Format::Text, options(), "stdin").unwrap();
+        assert!(!truncated);
+
+        let poem = finish_poem(&bytes, truncated, Format::Text, options(), "stdin").unwrap();
+        assert_eq!(poem.hemistichs.len(), 10);
+        assert_eq!(poem, load_text(TEN_HEMISTICHS, options()).unwrap());
+    }
+
+    #[test]
+    fn oversized_reader_names_stdin() {
+        let options = InputOptions {
+            max_bytes: 100,
+            ..options()
+        };
+
+        let error =
+            read_poem(TEN_HEMISTICHS.as_bytes(), Format::Text, options, "stdin").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The input from stdin appears suspiciously large"
+        );
+    }
+
+    fn load_fixture(name: &str) -> Result<Poem> {
+        load_poem(&format!("tests/fixtures/{name}"), options())
+    }
+
+    #[test]
+    fn loads_json_hemistich_array() {
+        let poem = load_fixture("ten-hemistichs.json").unwrap();
+        assert_eq!(poem, load_text(TEN_HEMISTICHS, options()).unwrap());
+    }
+
+    #[test]
+    fn rejects_malformed_json() {
+        let error = load_fixture("malformed.json").unwrap_err().to_string();
+        assert!(error.starts_with("Could not parse JSON from tests/fixtures/malformed.json"));
+
+        let error = load_fixture("no-hemistichs.json").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The JSON from tests/fixtures/no-hemistichs.json has no \"hemistichs\" array"
+        );
+
+        let error = load_fixture("empty-hemistichs.json").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The \"hemistichs\" array in the JSON from tests/fixtures/empty-hemistichs.json is empty"
+        );
+    }
+
+    #[test]
+    fn strips_bom_and_crlf() {
+        let unix = load_text(TEN_HEMISTICHS, options()).unwrap();
+
+        let windows = format!("\u{feff}{}", TEN_HEMISTICHS.replace('\n', "\r\n"));
+        let (bytes, truncated) =
+            read_poem(windows.as_bytes(), Format::Text, options(), "stdin").unwrap();
+        let poem = finish_poem(&bytes, truncated, Format::Text, options(), "stdin").unwrap();
+        assert_eq!(poem, unix);
+
+        let lone_cr = TEN_HEMISTICHS.replace('\n', "\r");
+        assert_eq!(load_text(&lone_cr, options()).unwrap(), unix);
+    }
+
+    #[test]
+    fn falls_back_to_windows_1256() {
+        // Windows-1256 has only the Arabic form of ye
+        let text = TEN_HEMISTICHS.replace('ی', "ي");
+        let (bytes, _, unmappable) = WINDOWS_1256.encode(&text);
+        assert!(!unmappable);
+        assert!(std::str::from_utf8(&bytes).is_err());
+
+        let poem = finish_poem(&bytes, false, Format::Text, options(), "stdin").unwrap();
+        assert_eq!(poem.encoding, Some("windows-1256"));
+        assert_eq!(
+            poem.hemistichs,
+            load_text(&text, options()).unwrap().hemistichs
+        );
+    }
+
+    #[test]
+    fn truncates_long_poem_to_complete_lines() {
+        let masnavi = TEN_HEMISTICHS.repeat(20);
+        assert!(masnavi.len() as u64 > MAX_FILE_SIZE);
+
+        let (bytes, truncated) =
+            read_poem(masnavi.as_bytes(), Format::Text, options(), "stdin").unwrap();
+        assert!(truncated);
+        assert_eq!(bytes, TEN_HEMISTICHS.repeat(4).as_bytes());
+
+        let poem = finish_poem(&bytes, truncated, Format::Text, options(), "stdin").unwrap();
+        assert_eq!(poem.hemistichs.len(), MAX_HEMISTICHS);
+        assert!(poem.truncated);
+
+        // Blank lines don't count toward the forty
+        let spaced = TEN_HEMISTICHS.replace('\n', "\n\n").repeat(20);
+        let (bytes, _) = read_poem(spaced.as_bytes(), Format::Text, options(), "stdin").unwrap();
+        let poem = finish_poem(&bytes, true, Format::Text, options(), "stdin").unwrap();
+        assert_eq!(poem.hemistichs.len(), 2 * MAX_HEMISTICHS - 1);
+
+        // Too few lines before the limit are still too many bytes
+        let options = InputOptions {
+            max_bytes: 1000,
+            ..options()
+        };
+        let error = read_poem(masnavi.as_bytes(), Format::Text, options, "stdin").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The input from stdin appears suspiciously large"
+        );
+    }
+
+    #[test]
+    fn directories_include_long_files() {
+        // However large, each file is listed, to be read as when given by
+        // name
+        let options = InputOptions {
+            max_bytes: 2600,
+            ..options()
+        };
+        let mut input_files = Vec::new();
+        collect_dir(Path::new("tests/fixtures"), &mut input_files).unwrap();
+        assert!(input_files
+            .iter()
+            .all(|file| matches!(file, InputFile::Analyze(_))));
+        assert!(input_files.iter().any(|file| matches!(
+            file,
+            InputFile::Analyze(path) if path.ends_with("tarji-band-constructed.txt")
+        )));
+
+        // One of them, at 2,782 bytes, is read as far as its first forty lines
+        let poem = load_poem("tests/fixtures/tarji-band-constructed.txt", options).unwrap();
+        assert_eq!(poem.hemistichs.len(), MAX_HEMISTICHS);
+        assert!(poem.truncated);
+    }
+
+    #[test]
+    fn rejects_long_input_without_enough_lines() {
+        let one_line = TEN_HEMISTICHS.replace('\n', " ").repeat(20);
+        assert!(read_poem(one_line.as_bytes(), Format::Text, options(), "stdin").is_err());
+
+        let masnavi = TEN_HEMISTICHS.repeat(20);
+        assert!(read_poem(masnavi.as_bytes(), Format::Json, options(), "stdin").is_err());
+    }
+
+    fn texts(poem: &Poem) -> Vec<&str> {
+        poem.hemistichs
+            .iter()
+            .map(|hem| hem.text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn loads_ganjoor_export_in_order() {
+        let poem = load_fixture("saib-6583-ganjoor.json").unwrap();
+        let plain = load_poem("saib-6583.txt", options()).unwrap();
+        assert_eq!(texts(&poem), texts(&plain));
+
+        for (i, hem) in poem.hemistichs.iter().enumerate() {
+            let misra = if i % 2 == 0 {
+                Misra::First
+            } else {
+                Misra::Second
+            };
+            assert_eq!(hem.misra, Some(misra));
+        }
+    }
+
+    #[test]
+    fn strips_html_markup() {
+        let poem = load_fixture("saib-6583.html").unwrap();
+        let plain = load_poem("saib-6583.txt", options()).unwrap();
+        assert_eq!(poem.hemistichs[0].line_no, 11);
+
+        // The page punctuates two hemistichs differently, with entities
+        let mut expected = texts(&plain);
+        expected[2] = "فریب روی آتشناک او خوردم — ندانستم…";
+        expected[7] = "برافکن از عذار خود «نقاب» آهسته آهسته";
+        assert_eq!(texts(&poem), expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn directory_links_are_not_followed() {
+        let dir = std::env::temp_dir().join(format!("persian-meter-links-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("poems")).unwrap();
+        fs::write(dir.join("poems/a.txt"), TEN_HEMISTICHS).unwrap();
+        std::os::unix::fs::symlink(".", dir.join("poems/loop")).unwrap();
+        std::os::unix::fs::symlink("..", dir.join("poems/up")).unwrap();
+
+        let mut input_files = Vec::new();
+        let result = collect_dir(&dir.join("poems"), &mut input_files);
+        fs::remove_dir_all(&dir).unwrap();
+        result.unwrap();
+
+        let listed: Vec<String> = input_files
+            .iter()
+            .map(|file| match file {
+                InputFile::Analyze(path) => format!("analyze {path}"),
+                InputFile::Archived(..) => unreachable!(),
+                InputFile::Skip(path, reason) => format!("skip {path}: {reason}"),
+            })
+            .collect();
+        let poems = dir.join("poems");
+        assert_eq!(
+            listed,
+            [
+                format!("analyze {}", poems.join("a.txt").display()),
+                format!("skip {}: link to a directory", poems.join("loop").display()),
+                format!("skip {}: link to a directory", poems.join("up").display()),
+            ]
+        );
+    }
+
+    #[test]
+    fn decodes_named_entities() {
+        for (entity, c) in [
+            ("&laquo;", '«'),
+            ("&raquo;", '»'),
+            ("&hellip;", '…'),
+            ("&mdash;", '—'),
+            ("&zwnj;", '\u{200c}'),
+            ("&ZeroWidthSpace;", '\u{200b}'),
+            ("&#1570;", 'آ'),
+            ("&#x622;", 'آ'),
+        ] {
+            assert_eq!(decode_entity(entity), Some((c, entity.len())), "{entity}");
+        }
+
+        // Anything else is left as it is, to be flagged
+        assert_eq!(decode_entity("&unknown;"), None);
+        assert_eq!(decode_entity("& و"), None);
+    }
+}
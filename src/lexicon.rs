@@ -0,0 +1,261 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::fs;
+
+use crate::CONSONANTS;
+
+// Hemistich-initial clues built into the program, to which --lexicon adds
+const DEFAULT_LEXICON: &str = include_str!("lexicon.toml");
+
+// What a clue says about the length of a syllable
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Implication {
+    LongFirst,
+    ShortFirst,
+    LongSecond,
+    ShortSecond,
+}
+
+impl Implication {
+    const fn is_first(self) -> bool {
+        matches!(self, Self::LongFirst | Self::ShortFirst)
+    }
+}
+
+// A word (or words) at the start of a hemistich, and what it implies
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InitialClue {
+    letters: String,
+    #[serde(default)]
+    requires_space: bool,
+    #[serde(default)]
+    requires_following_consonant: bool,
+    pub implies: Vec<Implication>,
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+}
+
+// An ordinary clue weighs 1
+const fn default_weight() -> f64 {
+    1.0
+}
+
+impl InitialClue {
+    fn matches(&self, hem_reconst: &[char]) -> bool {
+        let letters: Vec<char> = self.letters.chars().collect();
+        let Some(mut rest) = hem_reconst.strip_prefix(letters.as_slice()) else {
+            return false;
+        };
+
+        if self.requires_space {
+            match rest.split_first() {
+                Some((' ', after)) => rest = after,
+                _ => return false,
+            }
+        }
+
+        !self.requires_following_consonant || rest.first().is_some_and(|c| CONSONANTS.contains(c))
+    }
+
+    // The name of the entry as a rule, as shown with --explain
+    pub fn name(&self) -> String {
+        format!("lexicon:{}", self.letters)
+    }
+
+    // Only an entry about the first syllable alone can be checked after an
+    // opening monosyllable, for the second syllable
+    fn is_first_only(&self) -> bool {
+        self.implies
+            .iter()
+            .all(|implication| implication.is_first())
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Lexicon {
+    #[serde(default, rename = "entry")]
+    entries: Vec<InitialClue>,
+}
+
+impl Lexicon {
+    // The built-in lexicon, with the entries of any file given added to (or
+    // replacing) it
+    pub fn load(path: Option<&str>, replace: bool) -> Result<Self> {
+        let mut lexicon = if replace {
+            Self::default()
+        } else {
+            Self::parse(DEFAULT_LEXICON, "the built-in lexicon")?
+        };
+
+        if let Some(path) = path {
+            let contents = fs::read_to_string(path)
+                .map_err(|e| anyhow!("Could not read lexicon {path}: {e}"))?;
+            lexicon
+                .entries
+                .extend(Self::parse(&contents, path)?.entries);
+        }
+
+        Ok(lexicon)
+    }
+
+    fn parse(contents: &str, source: &str) -> Result<Self> {
+        let mut lexicon: Self =
+            toml::from_str(contents).map_err(|e| anyhow!("Could not parse {source}: {e}"))?;
+
+        // Letters are matched against reconstructed hemistichs, so they are
+        // normalized in the same way, and anything else is refused
+        for entry in &mut lexicon.entries {
+            entry.letters = entry
+                .letters
+                .trim()
+                .chars()
+                .map(|c| match c {
+                    'ي' | 'ى' => 'ی',
+                    'ك' => 'ک',
+                    _ => c,
+                })
+                .collect();
+
+            if entry.letters.is_empty()
+                || entry
+                    .letters
+                    .chars()
+                    .any(|c| !CONSONANTS.contains(&c) && !matches!(c, 'ا' | 'آ' | 'و' | 'ی' | ' '))
+            {
+                return Err(anyhow!(
+                    "Could not parse {source}: \"{}\" is not a sequence of Persian letters",
+                    entry.letters
+                ));
+            }
+            if entry.implies.is_empty() {
+                return Err(anyhow!(
+                    "Could not parse {source}: \"{}\" implies nothing",
+                    entry.letters
+                ));
+            }
+            if !entry.weight.is_finite() || entry.weight <= 0.0 {
+                return Err(anyhow!(
+                    "Could not parse {source}: \"{}\" has a weight that is not positive",
+                    entry.letters
+                ));
+            }
+        }
+
+        Ok(lexicon)
+    }
+
+    // The heaviest entry about the first syllable alone that matches the
+    // start of a hemistich (or of what follows an opening monosyllable), if
+    // any does
+    pub fn first_syllable(
+        &self,
+        hem_reconst: &[char],
+        implication: Implication,
+    ) -> Option<&InitialClue> {
+        self.entries
+            .iter()
+            .filter(|entry| {
+                entry.is_first_only()
+                    && entry.implies.contains(&implication)
+                    && entry.matches(hem_reconst)
+            })
+            .max_by(|a, b| a.weight.total_cmp(&b.weight))
+    }
+
+    // The entries about the second syllable that match the start of a
+    // hemistich
+    pub fn initial_clues<'a>(
+        &'a self,
+        hem_reconst: &'a [char],
+    ) -> impl Iterator<Item = &'a InitialClue> {
+        self.entries
+            .iter()
+            .filter(|entry| !entry.is_first_only() && entry.matches(hem_reconst))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXTRA: &str = "tests/fixtures/lexicon-extra.toml";
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    fn first_syllable(lexicon: &Lexicon, hem: &str, implication: Implication) -> Option<String> {
+        lexicon
+            .first_syllable(&chars(hem), implication)
+            .map(InitialClue::name)
+    }
+
+    #[test]
+    fn built_in_lexicon_parses() {
+        let lexicon = Lexicon::load(None, false).unwrap();
+        assert!(!lexicon.entries.is_empty());
+        assert_eq!(
+            first_syllable(&lexicon, "این چه شوریست", Implication::LongFirst).as_deref(),
+            Some("lexicon:این")
+        );
+        // "Az" must be followed by a consonant
+        assert!(first_syllable(&lexicon, "از دل برود", Implication::LongFirst).is_some());
+        assert!(first_syllable(&lexicon, "از آن", Implication::LongFirst).is_none());
+        assert!(first_syllable(&lexicon, "ازل", Implication::LongFirst).is_none());
+    }
+
+    #[test]
+    fn entries_are_added_or_replace_the_built_ins() {
+        let added = Lexicon::load(Some(EXTRA), false).unwrap();
+        let built_in = Lexicon::load(None, false).unwrap();
+        assert_eq!(added.entries.len(), built_in.entries.len() + 2);
+        assert!(first_syllable(&added, "این چه شوریست", Implication::LongFirst).is_some());
+
+        let replaced = Lexicon::load(Some(EXTRA), true).unwrap();
+        assert_eq!(replaced.entries.len(), 2);
+        assert!(first_syllable(&replaced, "این چه شوریست", Implication::LongFirst).is_none());
+
+        // Letters are normalized like a reconstructed hemistich, and the
+        // weight is kept
+        let gul = replaced
+            .first_syllable(&chars("گل سرخ"), Implication::LongFirst)
+            .unwrap();
+        assert_eq!(gul.name(), "lexicon:گل");
+        assert!((gul.weight - 1.5).abs() < f64::EPSILON);
+        assert_eq!(
+            first_syllable(&replaced, "کی دانست", Implication::ShortFirst).as_deref(),
+            Some("lexicon:کی")
+        );
+    }
+
+    #[test]
+    fn bad_entries_are_refused() {
+        let error = |contents: &str| {
+            Lexicon::parse(contents, "test.toml")
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(
+            error("[[entry]]\nletters = \"ab\"\nimplies = [\"long_first\"]\n"),
+            "Could not parse test.toml: \"ab\" is not a sequence of Persian letters"
+        );
+        assert_eq!(
+            error("[[entry]]\nletters = \"از\"\nimplies = []\n"),
+            "Could not parse test.toml: \"از\" implies nothing"
+        );
+        assert_eq!(
+            error("[[entry]]\nletters = \"از\"\nimplies = [\"long_first\"]\nweight = 0\n"),
+            "Could not parse test.toml: \"از\" has a weight that is not positive"
+        );
+        assert!(error("[[entry]]\nletters = \"از\"\nimplies = [\"long\"]\n")
+            .starts_with("Could not parse test.toml: "));
+        assert!(Lexicon::load(Some("tests/fixtures/missing.toml"), false)
+            .unwrap_err()
+            .to_string()
+            .starts_with("Could not read lexicon tests/fixtures/missing.toml: "));
+    }
+}